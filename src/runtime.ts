@@ -750,7 +750,7 @@ export function addProvenanceTable(ixer) {
 function mappingToDiff(diff, action, mapping, aliases, reverseLookup) {
   for(let from in mapping) {
     let to = mapping[from];
-    if(to.constructor === Array) {
+    if(to !== null && to.constructor === Array) {
       let source = to[0];
       if(typeof source === "number") {
         source = aliases[reverseLookup[source]];
@@ -920,7 +920,7 @@ export class Query {
         throw new Error(`Table '${tableName}' doesn't have a field '${field}'.\n\nAvailable fields: ${table.fields.join(", ")}`);
       }
       let joinInfo = joinObject[field];
-      if(joinInfo.constructor === Array) {
+      if(joinInfo !== null && joinInfo.constructor === Array) {
         let [joinNumber, referencedField] = joinInfo;
         if (typeof joinNumber !== "number") {
           joinNumber = this.aliases[joinNumber];
@@ -936,6 +936,24 @@ export class Query {
       }
     }
   }
+  validateArgs(funcName, args, aggregate = false) {
+    let funcInfo = QueryFunctions[funcName];
+    if(!funcInfo) {
+      throw new Error(`Unknown function '${funcName}'.`);
+    }
+    // aggregates receive their running state as the first param
+    let params = aggregate ? funcInfo.params.slice(1) : funcInfo.params;
+    for(let param of params) {
+      if(args[param] === undefined) {
+        throw new Error(`Function '${funcName}' is missing the argument '${param}'.\n\nExpected arguments: ${params.join(", ")}`);
+      }
+    }
+    for(let arg in args) {
+      if(params.indexOf(arg) === -1) {
+        throw new Error(`Function '${funcName}' doesn't take an argument '${arg}'.\n\nExpected arguments: ${params.join(", ")}`);
+      }
+    }
+  }
   select(table, join, as?) {
     this.dirty = true;
     if(as) {
@@ -956,6 +974,7 @@ export class Query {
   }
   calculate(funcName, args, as?) {
     this.dirty = true;
+    this.validateArgs(funcName, args);
     if(as) {
       this.aliases[as] = Object.keys(this.aliases).length;
     }
@@ -1002,6 +1021,7 @@ export class Query {
   }
   aggregate(funcName, args, as?) {
     this.dirty = true;
+    this.validateArgs(funcName, args, true);
     if(as) {
       this.aliases[as] = Object.keys(this.aliases).length;
     }
@@ -1018,7 +1038,7 @@ export class Query {
   applyAliases(joinMap) {
     for(let field in joinMap) {
       let joinInfo = joinMap[field];
-      if(joinInfo.constructor !== Array || typeof joinInfo[0] === "number") continue;
+      if(joinInfo === null || joinInfo.constructor !== Array || typeof joinInfo[0] === "number") continue;
       let joinTable = joinInfo[0];
       if(joinTable === "ordinal") {
         joinInfo[0] = this.unprojectedSize - 1;
@@ -1162,7 +1182,7 @@ export class Query {
     for(let param of params) {
       let arg = args[param];
       let argCode;
-      if(arg.constructor === Array) {
+      if(arg !== null && arg.constructor === Array) {
         let property = "";
         if(arg[1]) {
           property = `['${arg[1]}']`;
@@ -1220,7 +1240,7 @@ export class Query {
         } else if(root.join) {
           for(let key in root.join) {
             let mapping = root.join[key];
-            if(mapping.constructor === Array) {
+            if(mapping !== null && mapping.constructor === Array) {
               let [tableIx, value] = mapping;
               code += `query${ix}['${key}'] = row${tableIx}['${value}'];\n`;
             } else {
@@ -1395,7 +1415,7 @@ export class Query {
         for(let newField in root.projectionMap) {
           let mapping = root.projectionMap[newField];
           let value = "";
-          if(mapping.constructor === Array) {
+          if(mapping !== null && mapping.constructor === Array) {
             if(mapping[1] === undefined) {
               value = `unprojected[ix + ${mapping[0]}]`;
             } else if(!root.unprojected || root.unprojected[mapping[0]]) {
@@ -1444,7 +1464,9 @@ export class Query {
     // collect all the constraints and reverse them
     for (let join of joins) {
       for (let key in join.join) {
-        let [source, field] = join.join[key];
+        let mapping = join.join[key];
+        if (mapping === null || mapping.constructor !== Array) continue;
+        let [source, field] = mapping;
         if (source <= changed.ix) {
           if (!reverseJoinMap[source]) {
             reverseJoinMap[source] = {};
@@ -1472,7 +1494,7 @@ export class Query {
       }
       for(let key in joinMap) {
         let value = joinMap[key];
-        if(value.constructor !== Array) {
+        if(value === null || value.constructor !== Array) {
           mappings.push(`'${key}': ${JSON.stringify(value)}`);
         }
       }
@@ -1706,7 +1728,7 @@ export class Union {
         diff.add("action source", {action, "source view": source.table});
         for(let field in source.mapping) {
           let mapped = source.mapping[field];
-          if(mapped.constructor === Array) diff.add("action mapping", {action, from: field, "to source": source.table, "to field": mapped[0]})
+          if(mapped !== null && mapped.constructor === Array) diff.add("action mapping", {action, from: field, "to source": source.table, "to field": mapped[0]})
           else diff.add("action mapping constant", {action, from: field, value: mapped});
         }
 
@@ -1774,10 +1796,10 @@ export class Union {
         for(let key in root.mapping) {
           let mapping = root.mapping[key];
           let value;
-          if(mapping.constructor === Array && mapping.length === 1) {
+          if(mapping !== null && mapping.constructor === Array && mapping.length === 1) {
             let [field] = mapping;
             value = `sourceRow${ix}['${field}']`;
-          } else if(mapping.constructor === Array && mapping.length === 2) {
+          } else if(mapping !== null && mapping.constructor === Array && mapping.length === 2) {
             let [_, field] = mapping;
             value = `sourceRow${ix}['${field}']`;
           } else {
@@ -2019,6 +2041,20 @@ runtime.define("average", {aggregate: true, result: "average"}, function(prev, v
   return prev;
 });
 
+runtime.define("minimum", {aggregate: true, result: "minimum"}, function(prev, value) {
+  if(prev.minimum === undefined || value < prev.minimum) {
+    prev.minimum = value;
+  }
+  return prev;
+});
+
+runtime.define("maximum", {aggregate: true, result: "maximum"}, function(prev, value) {
+  if(prev.maximum === undefined || value > prev.maximum) {
+    prev.maximum = value;
+  }
+  return prev;
+});
+
 runtime.define("lowercase", {result: "result"}, function(text) {
   if(typeof text === "string") {
     return {result: text.toLowerCase()};
@@ -2026,6 +2062,182 @@ runtime.define("lowercase", {result: "result"}, function(text) {
   return {result: text};
 })
 
+runtime.define("concat", {result: "result"}, function(a, b) {
+  let aType = typeof a;
+  let bType = typeof b;
+  if((aType === "string" || aType === "number") && (bType === "string" || bType === "number")) {
+    return {result: `${a}${b}`};
+  }
+  return {result: a};
+});
+
+runtime.define("length", {result: "result"}, function(text) {
+  if(typeof text === "string") {
+    return {result: text.length};
+  }
+  return {result: text};
+});
+
+runtime.define("substring", {result: "result"}, function(text, from, to) {
+  if(typeof text === "string" && typeof from === "number" && typeof to === "number") {
+    return {result: text.substring(from, to)};
+  }
+  return {result: text};
+});
+
+runtime.define("replace", {result: "result"}, function(text, search, replacement) {
+  if(typeof text === "string" && typeof search === "string" && search !== "" && typeof replacement === "string") {
+    return {result: text.split(search).join(replacement)};
+  }
+  return {result: text};
+});
+
+// produces one row per token, in the order they appear in the text
+runtime.define("split", {multi: true}, function(text, separator) {
+  if(typeof text !== "string" || typeof separator !== "string") return [];
+  let results = [];
+  let ix = 0;
+  for(let token of text.split(separator)) {
+    results.push({result: token, ix});
+    ix++;
+  }
+  return results;
+});
+
+// patterns are compiled once per pattern/flags pair. Invalid patterns are cached
+// as null so a bad pattern just fails to match instead of throwing mid-query.
+// Patterns can come from facts, so the cache is dropped once it gets too big.
+var REGEX_CACHE_LIMIT = 1000;
+var regexCache = {};
+var regexCacheSize = 0;
+function cachedRegex(pattern, flags) {
+  if(typeof pattern !== "string") return null;
+  let key = `${flags}/${pattern}`;
+  let regex = regexCache[key];
+  if(regex === undefined) {
+    try {
+      regex = new RegExp(pattern, flags);
+    } catch(e) {
+      regex = null;
+    }
+    if(regexCacheSize >= REGEX_CACHE_LIMIT) {
+      regexCache = {};
+      regexCacheSize = 0;
+    }
+    regexCache[key] = regex;
+    regexCacheSize++;
+  }
+  return regex;
+}
+
+runtime.define("matches", {filter: true}, function(text, pattern) {
+  if(typeof text !== "string") return runtime.FAIL;
+  let regex = cachedRegex(pattern, "");
+  return regex && regex.test(text) ? runtime.SUCCEED : runtime.FAIL;
+});
+
+// produces one row per match, with the requested capture group as the result.
+// Matches where that group didn't participate are skipped.
+runtime.define("capture", {multi: true}, function(text, pattern, group) {
+  if(typeof text !== "string") return [];
+  let regex = cachedRegex(pattern, "g");
+  if(!regex || typeof group !== "number" || group % 1 !== 0 || group < 0) return [];
+  let results = [];
+  let match;
+  regex.lastIndex = 0;
+  while((match = regex.exec(text)) !== null) {
+    if(group >= match.length) return [];
+    if(match[group] !== undefined) {
+      results.push({result: match[group], ix: match.index});
+    }
+    // guard against zero-width matches looping forever
+    if(match[0] === "") regex.lastIndex++;
+  }
+  return results;
+});
+
+// times are millisecond timestamps (as from Date.getTime()), interpreted in UTC
+var FIXED_UNITS = {
+  milliseconds: 1,
+  seconds: 1000,
+  minutes: 60 * 1000,
+  hours: 60 * 60 * 1000,
+  days: 24 * 60 * 60 * 1000,
+  weeks: 7 * 24 * 60 * 60 * 1000,
+};
+var CALENDAR_UNITS = {months: 1, years: 12};
+
+// adding months clamps to the end of the month, so Jan 31 + 1 month is Feb 28/29
+function addMonths(time, months) {
+  let date = new Date(time);
+  let day = date.getUTCDate();
+  date.setUTCDate(1);
+  date.setUTCMonth(date.getUTCMonth() + months);
+  let lastDay = new Date(Date.UTC(date.getUTCFullYear(), date.getUTCMonth() + 1, 0)).getUTCDate();
+  date.setUTCDate(Math.min(day, lastDay));
+  return date.getTime();
+}
+
+// counts whole months elapsed between the two times. Backwards spans are measured
+// forwards and negated so that swapping the arguments only flips the sign.
+function monthsBetween(from, to) {
+  if(to < from) return -monthsBetween(to, from);
+  let a = new Date(from);
+  let b = new Date(to);
+  let months = (b.getUTCFullYear() - a.getUTCFullYear()) * 12 + b.getUTCMonth() - a.getUTCMonth();
+  if(months > 0 && addMonths(from, months) > to) months--;
+  return months;
+}
+
+function padNumber(value, width) {
+  let str = `${Math.abs(value)}`;
+  while(str.length < width) str = "0" + str;
+  return value < 0 ? "-" + str : str;
+}
+
+runtime.define("date add", {result: "result"}, function(time, amount, unit) {
+  if(typeof time !== "number" || typeof amount !== "number") return {result: time};
+  if(FIXED_UNITS[unit] !== undefined) {
+    return {result: time + amount * FIXED_UNITS[unit]};
+  }
+  if(CALENDAR_UNITS[unit] !== undefined && amount % 1 === 0) {
+    return {result: addMonths(time, amount * CALENDAR_UNITS[unit])};
+  }
+  return {result: time};
+});
+
+// fixed units give fractional differences, months and years count whole elapsed units.
+// Rows with non-numeric times or an unknown unit produce no result.
+runtime.define("date diff", {multi: true}, function(from, to, unit) {
+  if(typeof from !== "number" || typeof to !== "number") return [];
+  if(FIXED_UNITS[unit] !== undefined) {
+    return [{result: (to - from) / FIXED_UNITS[unit]}];
+  }
+  if(CALENDAR_UNITS[unit] !== undefined) {
+    let months = monthsBetween(from, to);
+    return [{result: months < 0 ? Math.ceil(months / CALENDAR_UNITS[unit]) : Math.floor(months / CALENDAR_UNITS[unit])}];
+  }
+  return [];
+});
+
+// supports YYYY, MM, DD, HH, mm, ss and SSS tokens
+runtime.define("format time", {result: "result"}, function(time, format) {
+  if(typeof time !== "number" || typeof format !== "string") return {result: time};
+  let date = new Date(time);
+  if(isNaN(date.getTime())) return {result: time};
+  return {result: format.replace(/YYYY|MM|DD|HH|mm|ss|SSS/g, function(token) {
+    switch(token) {
+      case "YYYY": return padNumber(date.getUTCFullYear(), 4);
+      case "MM": return padNumber(date.getUTCMonth() + 1, 2);
+      case "DD": return padNumber(date.getUTCDate(), 2);
+      case "HH": return padNumber(date.getUTCHours(), 2);
+      case "mm": return padNumber(date.getUTCMinutes(), 2);
+      case "ss": return padNumber(date.getUTCSeconds(), 2);
+      case "SSS": return padNumber(date.getUTCMilliseconds(), 3);
+    }
+  })};
+});
+
 runtime.define("=", {filter: true, inverse: "!="}, function(a, b) {
   return a === b ? runtime.SUCCEED : runtime.FAIL;
 });
@@ -2050,6 +2262,23 @@ runtime.define("<=", {filter: true, inverse: ">"}, function(a, b) {
   return a <= b ? runtime.SUCCEED : runtime.FAIL;
 });
 
+runtime.define("~=", {filter: true, inverse: "!~="}, function(a, b, tolerance) {
+  return Math.abs(a - b) <= tolerance ? runtime.SUCCEED : runtime.FAIL;
+});
+
+runtime.define("!~=", {filter: true, inverse: "~="}, function(a, b, tolerance) {
+  return Math.abs(a - b) > tolerance ? runtime.SUCCEED : runtime.FAIL;
+});
+
+// tolerance is a fraction of the larger magnitude, e.g. 0.01 for within 1%
+runtime.define("relative ~=", {filter: true, inverse: "relative !~="}, function(a, b, tolerance) {
+  return Math.abs(a - b) <= tolerance * Math.max(Math.abs(a), Math.abs(b)) ? runtime.SUCCEED : runtime.FAIL;
+});
+
+runtime.define("relative !~=", {filter: true, inverse: "relative ~="}, function(a, b, tolerance) {
+  return Math.abs(a - b) > tolerance * Math.max(Math.abs(a), Math.abs(b)) ? runtime.SUCCEED : runtime.FAIL;
+});
+
 runtime.define("+", {result: "result"}, function(a, b) {
   return {result: a + b};
 });
@@ -2070,6 +2299,34 @@ runtime.define("^", {result: "result"}, function(a, b) {
   return {result: Math.pow(a,b)};
 });
 
+runtime.define("if", {result: "result"}, function(condition, then, otherwise) {
+  return {result: condition ? then : otherwise};
+});
+
+runtime.define("and", {result: "result"}, function(a, b) {
+  return {result: !!(a && b)};
+});
+
+runtime.define("or", {result: "result"}, function(a, b) {
+  return {result: !!(a || b)};
+});
+
+runtime.define("not", {result: "result"}, function(value) {
+  return {result: !value};
+});
+
+// only numbers with numbers and strings with strings are comparable, anything else
+// (including NaN) produces no row rather than looking equal
+runtime.define("compare", {multi: true}, function(a, b) {
+  let aType = typeof a;
+  if(aType !== typeof b || (aType !== "number" && aType !== "string") || a !== a || b !== b) return [];
+  return [{result: a < b ? -1 : (a > b ? 1 : 0)}];
+});
+
+runtime.define("coalesce", {result: "result"}, function(value, fallback) {
+  return {result: value === undefined || value === null ? fallback : value};
+});
+
 //---------------------------------------------------------
 // AST and compiler
 //---------------------------------------------------------